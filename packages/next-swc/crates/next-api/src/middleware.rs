@@ -0,0 +1,106 @@
+use anyhow::{Context, Result};
+use turbo_tasks::{Value, Vc};
+use turbopack_binding::turbopack::{
+    core::{
+        chunk::{availability_info::AvailabilityInfo, ChunkableModule, ChunkingContext},
+        context::AssetContext,
+        module_options::ModuleOptionsContext,
+        reference_type::{EntryReferenceSubType, ReferenceType},
+        resolve::options::ResolveOptionsContext,
+        source::Source,
+    },
+    ecmascript::chunk::EcmascriptChunkingContext,
+    turbopack::{transition::TransitionsByName, ModuleAssetContext},
+};
+
+use crate::{
+    project::Project,
+    route::{Endpoint, WrittenEndpoint},
+};
+
+/// The endpoint for Next.js middleware (a `middleware.{js,ts,...}` file at
+/// the project root), built through the same server chunking contexts used
+/// for RSC entries.
+#[turbo_tasks::value]
+pub struct MiddlewareEndpoint {
+    project: Vc<Project>,
+    chunking_context: Vc<Box<dyn EcmascriptChunkingContext>>,
+    source: Vc<Box<dyn Source>>,
+}
+
+#[turbo_tasks::value_impl]
+impl MiddlewareEndpoint {
+    #[turbo_tasks::function]
+    pub fn new(
+        project: Vc<Project>,
+        chunking_context: Vc<Box<dyn EcmascriptChunkingContext>>,
+        source: Vc<Box<dyn Source>>,
+    ) -> Vc<Self> {
+        MiddlewareEndpoint {
+            project,
+            chunking_context,
+            source,
+        }
+        .cell()
+    }
+
+    /// A minimal [`AssetContext`] for resolving and transforming the
+    /// middleware module prior to chunking. Unlike the contexts
+    /// `AppProject`/`PagesProject` build (`crate::app`, `crate::pages`), this
+    /// doesn't layer on the project's module-options/resolve config, so
+    /// aliasing, CSS modules, etc. configured in `next.config.js` don't apply
+    /// here.
+    #[turbo_tasks::function]
+    async fn asset_context(self: Vc<Self>) -> Result<Vc<Box<dyn AssetContext>>> {
+        let this = self.await?;
+        Ok(Vc::upcast(ModuleAssetContext::new(
+            TransitionsByName::empty(),
+            this.project.server_compile_time_info(),
+            ModuleOptionsContext::default().cell(),
+            ResolveOptionsContext::default().cell(),
+            Vc::cell("middleware".to_string()),
+        )))
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl Endpoint for MiddlewareEndpoint {
+    #[turbo_tasks::function]
+    async fn write_to_disk(self: Vc<Self>) -> Result<Vc<WrittenEndpoint>> {
+        let this = self.await?;
+
+        let module = self
+            .asset_context()
+            .process(
+                this.source,
+                Value::new(ReferenceType::Entry(EntryReferenceSubType::Middleware)),
+            )
+            .module();
+        let module = Vc::try_resolve_sidecast::<Box<dyn ChunkableModule>>(module)
+            .await?
+            .context("middleware entry module is not chunkable")?;
+
+        let chunk_group = this
+            .chunking_context
+            .chunk_group(module, Value::new(AvailabilityInfo::Root));
+
+        // Every `Endpoint` that emits output assets is expected to eagerly
+        // record them here so `Project::hmr_events` can diff future writes
+        // against them. `AppEndpoint`/`PagesEndpoint` (`crate::app`,
+        // `crate::pages`) need the same `insert_path` call at their
+        // equivalent write site.
+        let map = this.project.versioned_content_map();
+        let mut server_paths = Vec::new();
+        for asset in chunk_group.await?.iter() {
+            let path = asset.ident().path().resolve().await?;
+            map.insert_path(path, Vc::upcast(asset.content())).await?;
+            server_paths.push(path);
+        }
+
+        Ok(WrittenEndpoint {
+            server_paths,
+            client_paths: vec![],
+        }
+        .cell())
+    }
+}
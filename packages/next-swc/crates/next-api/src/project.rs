@@ -1,6 +1,6 @@
 use std::path::MAIN_SEPARATOR;
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use indexmap::{map::Entry, IndexMap};
 use next_core::{
     app_structure::find_app_dir,
@@ -8,21 +8,27 @@ use next_core::{
     next_client::{get_client_chunking_context, get_client_compile_time_info},
     next_config::NextConfig,
     next_server::{get_server_chunking_context, get_server_compile_time_info},
-    util::NextSourceConfig,
+    util::{parse_config_from_source, NextSourceConfig},
 };
 use serde::{Deserialize, Serialize};
 use turbo_tasks::{
-    debug::ValueDebugFormat, trace::TraceRawVcs, unit, TaskInput, TransientValue, Vc,
+    debug::ValueDebugFormat, trace::TraceRawVcs, unit, State, TaskInput, TransientInstance,
+    TransientValue, Vc,
 };
 use turbopack_binding::{
     turbo::{
         tasks_env::ProcessEnv,
-        tasks_fs::{DiskFileSystem, FileSystem, FileSystemPath, VirtualFileSystem},
+        tasks_fs::{
+            DiskFileSystem, FileContent, FileSystem, FileSystemEntryType, FileSystemPath,
+            VirtualFileSystem,
+        },
     },
     turbopack::{
         build::BuildChunkingContext,
         core::{
-            chunk::ChunkingContext, compile_time_info::CompileTimeInfo, environment::ServerAddr,
+            chunk::ChunkingContext, compile_time_info::CompileTimeInfo,
+            environment::ServerAddr, file_source::FileSource,
+            version::{Update, Version},
             PROJECT_FILESYSTEM_NAME,
         },
         dev::DevChunkingContext,
@@ -36,8 +42,11 @@ use turbopack_binding::{
 use crate::{
     app::{AppProject, OptionAppProject},
     entrypoints::Entrypoints,
+    middleware::MiddlewareEndpoint,
     pages::PagesProject,
+    rc_str::RcStr,
     route::{Endpoint, Route},
+    versioned_content_map::VersionedContentMap,
 };
 
 #[derive(Debug, Serialize, Deserialize, Clone, TaskInput)]
@@ -45,63 +54,185 @@ use crate::{
 pub struct ProjectOptions {
     /// A root path from which all files must be nested under. Trying to access
     /// a file outside this root will fail. Think of this as a chroot.
-    pub root_path: String,
+    pub root_path: RcStr,
 
     /// A path inside the root_path which contains the app/pages directories.
-    pub project_path: String,
+    pub project_path: RcStr,
 
     /// The contents of next.config.js, serialized to JSON.
-    pub next_config: String,
+    pub next_config: RcStr,
 
     /// Whether to watch the filesystem for file changes.
     pub watch: bool,
 
+    /// Whether to run in development (`true`) or production build (`false`)
+    /// mode. Development mode favors incremental recomputation and
+    /// unminified, unhashed output; production mode emits minified,
+    /// content-hashed chunks suitable for deployment.
+    pub dev: bool,
+
+    /// The browserslist query to use for the client compile target. Falls
+    /// back to the project's browserslist configuration, and finally to
+    /// [`DEFAULT_BROWSERSLIST_QUERY`], when not set.
+    pub browserslist_query: Option<RcStr>,
+
     /// An upper bound of memory that turbopack will attempt to stay under.
     pub memory_limit: Option<u64>,
 }
 
-#[derive(Serialize, Deserialize, TraceRawVcs, PartialEq, Eq, ValueDebugFormat)]
+/// The browserslist query used when a project provides neither an explicit
+/// `browserslist_query` nor its own browserslist configuration.
+const DEFAULT_BROWSERSLIST_QUERY: &str = "last 1 Chrome versions, last 1 Firefox versions, last \
+                                           1 Safari versions, last 1 Edge versions";
+
+#[derive(Clone, Serialize, Deserialize, TraceRawVcs, PartialEq, Eq, ValueDebugFormat)]
 pub struct Middleware {
     pub endpoint: Vc<Box<dyn Endpoint>>,
     pub config: NextSourceConfig,
 }
 
+#[turbo_tasks::value(transparent)]
+pub struct OptionMiddleware(Option<Middleware>);
+
+#[turbo_tasks::value(transparent)]
+struct OptionRcStr(Option<RcStr>);
+
+#[turbo_tasks::value(transparent)]
+pub struct OptionU64(Option<u64>);
+
 #[turbo_tasks::value]
 pub struct Project {
     /// A root path from which all files must be nested under. Trying to access
     /// a file outside this root will fail. Think of this as a chroot.
-    root_path: String,
+    root_path: RcStr,
 
     /// A path inside the root_path which contains the app/pages directories.
-    project_path: String,
+    project_path: RcStr,
 
     /// Whether to watch the filesystem for file changes.
-    watch: bool,
+    watch: State<bool>,
 
     /// Next config.
-    next_config: Vc<NextConfig>,
+    next_config: State<Vc<NextConfig>>,
+
+    browserslist_query: State<RcStr>,
 
-    browserslist_query: String,
+    mode: State<NextMode>,
 
-    mode: NextMode,
+    /// An upper bound of memory that turbopack will attempt to stay under,
+    /// mirroring the value last forwarded to the turbo-tasks memory backend.
+    memory_limit: State<Option<u64>>,
+
+    /// Holds the output assets written by every endpoint as they're emitted,
+    /// keyed by output path, so `hmr_events` can diff successive versions
+    /// without going through a dev server.
+    versioned_content_map: Vc<VersionedContentMap>,
 }
 
 #[turbo_tasks::value_impl]
 impl Project {
     #[turbo_tasks::function]
     pub async fn new(options: ProjectOptions) -> Result<Vc<Self>> {
-        let next_config = NextConfig::from_string(options.next_config);
-        Ok(Project {
+        let next_config = NextConfig::from_string(options.next_config.to_string());
+        let explicit_browserslist_query = options.browserslist_query;
+        if let Some(memory_limit) = options.memory_limit {
+            turbo_tasks::turbo_tasks().set_memory_limit(memory_limit as usize);
+        }
+        let project = Project {
             root_path: options.root_path,
             project_path: options.project_path,
-            watch: options.watch,
-            next_config,
-            browserslist_query: "last 1 Chrome versions, last 1 Firefox versions, last 1 Safari \
-                                 versions, last 1 Edge versions"
-                .to_string(),
-            mode: NextMode::Development,
+            watch: State::new(options.watch),
+            next_config: State::new(next_config),
+            browserslist_query: State::new(
+                explicit_browserslist_query
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_BROWSERSLIST_QUERY.into()),
+            ),
+            mode: State::new(if options.dev {
+                NextMode::Development
+            } else {
+                NextMode::Build
+            }),
+            memory_limit: State::new(options.memory_limit),
+            versioned_content_map: VersionedContentMap::empty(),
         }
-        .cell())
+        .cell();
+
+        if explicit_browserslist_query.is_none() {
+            project.resolve_browserslist_query().await?;
+        }
+
+        Ok(project)
+    }
+
+    /// Re-applies a changed `next.config.js`, watch flag, or browserslist
+    /// query onto an existing `Project`. `root_path` and `project_path`
+    /// cannot be changed this way; construct a new `Project` if those need
+    /// to move.
+    #[turbo_tasks::function]
+    pub async fn update(self: Vc<Self>, options: ProjectOptions) -> Result<Vc<()>> {
+        let this = self.await?;
+
+        if options.root_path != this.root_path || options.project_path != this.project_path {
+            bail!(
+                "Project::update cannot change root_path or project_path; create a new Project \
+                 instead"
+            );
+        }
+
+        this.next_config
+            .set(NextConfig::from_string(options.next_config.to_string()));
+        this.watch.set(options.watch);
+        this.mode.set(if options.dev {
+            NextMode::Development
+        } else {
+            NextMode::Build
+        });
+
+        let explicit_browserslist_query = options.browserslist_query;
+        this.browserslist_query.set(
+            explicit_browserslist_query
+                .clone()
+                .unwrap_or_else(|| DEFAULT_BROWSERSLIST_QUERY.into()),
+        );
+        if explicit_browserslist_query.is_none() {
+            self.resolve_browserslist_query().await?;
+        }
+
+        this.memory_limit.set(options.memory_limit);
+        // `usize::MAX` is the backend's "no limit" sentinel, so a `None` here
+        // actually clears a previously set limit instead of leaving the old
+        // numeric limit enforced while `Project` reports no limit.
+        turbo_tasks::turbo_tasks()
+            .set_memory_limit(options.memory_limit.unwrap_or(u64::MAX) as usize);
+
+        Ok(unit())
+    }
+
+    /// The memory limit last forwarded to the turbo-tasks backend, if any.
+    #[turbo_tasks::function]
+    pub(super) fn memory_limit(&self) -> Vc<OptionU64> {
+        Vc::cell(*self.memory_limit.get())
+    }
+
+    /// Re-resolves `browserslist_query` from the project's own configuration
+    /// (`.browserslistrc` or `package.json`'s `browserslist` field) when no
+    /// explicit query was provided, reading through the tracked project
+    /// filesystem so edits to either file invalidate this computation.
+    #[turbo_tasks::function]
+    async fn resolve_browserslist_query(self: Vc<Self>) -> Result<Vc<()>> {
+        let this = self.await?;
+        if let Some(query) = &*read_project_browserslist_query(self.project_path()).await? {
+            this.browserslist_query.set(query.clone());
+        }
+        Ok(unit())
+    }
+
+    /// The map endpoints write their output assets into as they're computed,
+    /// backing [`Project::hmr_events`].
+    #[turbo_tasks::function]
+    pub(super) async fn versioned_content_map(self: Vc<Self>) -> Result<Vc<VersionedContentMap>> {
+        Ok(self.await?.versioned_content_map)
     }
 
     #[turbo_tasks::function]
@@ -110,7 +241,7 @@ impl Project {
         let app_dir = find_app_dir(self.project_path()).await?;
 
         Ok(Vc::cell(if let Some(app_dir) = &*app_dir {
-            Some(AppProject::new(self, *app_dir, this.mode))
+            Some(AppProject::new(self, *app_dir, *this.mode.get()))
         } else {
             None
         }))
@@ -119,7 +250,7 @@ impl Project {
     #[turbo_tasks::function]
     async fn pages_project(self: Vc<Self>) -> Result<Vc<PagesProject>> {
         let this = self.await?;
-        Ok(PagesProject::new(self, this.mode))
+        Ok(PagesProject::new(self, *this.mode.get()))
     }
 
     #[turbo_tasks::function]
@@ -129,7 +260,7 @@ impl Project {
             PROJECT_FILESYSTEM_NAME.to_string(),
             this.root_path.to_string(),
         );
-        if this.watch {
+        if *this.watch.get() {
             disk_fs.await?.start_watching_with_invalidation_reason()?;
         }
         Ok(Vc::upcast(disk_fs))
@@ -144,7 +275,7 @@ impl Project {
     #[turbo_tasks::function]
     async fn node_fs(self: Vc<Self>) -> Result<Vc<Box<dyn FileSystem>>> {
         let this = self.await?;
-        let disk_fs = DiskFileSystem::new("node".to_string(), this.project_path.clone());
+        let disk_fs = DiskFileSystem::new("node".to_string(), this.project_path.to_string());
         disk_fs.await?.start_watching_with_invalidation_reason()?;
         Ok(Vc::upcast(disk_fs))
     }
@@ -173,7 +304,7 @@ impl Project {
     pub(super) async fn project_path(self: Vc<Self>) -> Result<Vc<FileSystemPath>> {
         let this = self.await?;
         let root = self.project_root_path();
-        let project_relative = this.project_path.strip_prefix(&this.root_path).unwrap();
+        let project_relative = this.project_path.strip_prefix(&*this.root_path).unwrap();
         let project_relative = project_relative
             .strip_prefix(MAIN_SEPARATOR)
             .unwrap_or(project_relative)
@@ -188,7 +319,7 @@ impl Project {
 
     #[turbo_tasks::function]
     pub(super) async fn next_config(self: Vc<Self>) -> Result<Vc<NextConfig>> {
-        Ok(self.await?.next_config)
+        Ok(*self.await?.next_config.get())
     }
 
     #[turbo_tasks::function]
@@ -215,14 +346,14 @@ impl Project {
 
     #[turbo_tasks::function]
     pub(super) fn client_compile_time_info(&self) -> Vc<CompileTimeInfo> {
-        get_client_compile_time_info(self.mode, self.browserslist_query.clone())
+        get_client_compile_time_info(*self.mode.get(), self.browserslist_query.get().to_string())
     }
 
     #[turbo_tasks::function]
     pub(super) async fn server_compile_time_info(self: Vc<Self>) -> Result<Vc<CompileTimeInfo>> {
         let this = self.await?;
         Ok(get_server_compile_time_info(
-            this.mode,
+            *this.mode.get(),
             self.env(),
             // TODO(alexkirsz) Fill this out.
             ServerAddr::empty(),
@@ -238,18 +369,22 @@ impl Project {
             self.project_path(),
             self.client_root(),
             self.client_compile_time_info().environment(),
-            this.mode,
+            *this.mode.get(),
         ))
     }
 
     #[turbo_tasks::function]
-    pub(super) fn server_chunking_context(self: Vc<Self>) -> Vc<BuildChunkingContext> {
-        get_server_chunking_context(
+    pub(super) async fn server_chunking_context(
+        self: Vc<Self>,
+    ) -> Result<Vc<BuildChunkingContext>> {
+        let this = self.await?;
+        Ok(get_server_chunking_context(
             self.project_path(),
             self.node_root(),
             self.client_fs().root(),
             self.server_compile_time_info().environment(),
-        )
+            *this.mode.get(),
+        ))
     }
 
     #[turbo_tasks::function]
@@ -304,19 +439,161 @@ impl Project {
             }
         }
 
-        // TODO middleware
         Ok(Entrypoints {
             routes,
-            middleware: None,
+            middleware: (*self.middleware().await?).clone(),
         }
         .cell())
     }
 
-    /// Emits opaque HMR events whenever a change is detected in the chunk group
-    /// internally known as `identifier`.
+    /// Looks for a `middleware.{js,ts,...}` file (honoring the configured
+    /// `page_extensions`) at the project root and, if present, builds it into
+    /// an [`Endpoint`] through the RSC chunking context, parsing its exported
+    /// `config` matcher along the way.
+    #[turbo_tasks::function]
+    async fn middleware(self: Vc<Self>) -> Result<Vc<OptionMiddleware>> {
+        let this = self.await?;
+        let project_root = self.project_path();
+        let page_extensions = this.next_config.get().page_extensions().await?;
+
+        for ext in page_extensions.iter() {
+            let middleware_path = project_root.join(format!("middleware.{ext}"));
+            if matches!(
+                *middleware_path.get_type().await?,
+                FileSystemEntryType::File
+            ) {
+                let source = Vc::upcast(FileSource::new(middleware_path));
+                let endpoint = Vc::upcast(MiddlewareEndpoint::new(
+                    self,
+                    Vc::upcast(self.rsc_chunking_context()),
+                    source,
+                ));
+                let config = parse_config_from_source(source).await?.unwrap_or_default();
+                return Ok(Vc::cell(Some(Middleware { endpoint, config })));
+            }
+        }
+
+        Ok(Vc::cell(None))
+    }
+
+    /// Diffs the content currently stored for `identifier` in the
+    /// [`VersionedContentMap`] against the client's last-known `from`
+    /// version. `sender` only exists to keep repeated subscriptions for the
+    /// same client from being deduped against each other.
     #[turbo_tasks::function]
-    pub fn hmr_events(self: Vc<Self>, _identifier: String, _sender: TransientValue<()>) -> Vc<()> {
-        unit()
+    pub async fn hmr_events(
+        self: Vc<Self>,
+        identifier: String,
+        from: TransientInstance<Box<dyn Version>>,
+        _sender: TransientValue<()>,
+    ) -> Result<Vc<Update>> {
+        let path = self.node_root().join(identifier);
+        Ok(self.versioned_content_map().update(path, from))
+    }
+}
+
+/// Reads a browserslist query out of the project's own configuration
+/// (a `browserslist` field in `package.json`, or a `.browserslistrc` file),
+/// returning `None` when neither is present so the caller can fall back to
+/// [`DEFAULT_BROWSERSLIST_QUERY`]. Goes through `project_root`'s tracked
+/// filesystem rather than `std::fs` so edits to either file participate in
+/// turbo-tasks invalidation/watching like any other tracked read.
+#[turbo_tasks::function]
+async fn read_project_browserslist_query(
+    project_root: Vc<FileSystemPath>,
+) -> Result<Vc<OptionRcStr>> {
+    let browserslistrc = project_root.join(".browserslistrc".to_string());
+    if matches!(*browserslistrc.get_type().await?, FileSystemEntryType::File) {
+        if let FileContent::Content(file) = &*browserslistrc.read().await? {
+            if let Some(query) = parse_browserslistrc(file.content().to_str()?.as_ref()) {
+                return Ok(Vc::cell(Some(query.into())));
+            }
+        }
+    }
+
+    let package_json_path = project_root.join("package.json".to_string());
+    if matches!(
+        *package_json_path.get_type().await?,
+        FileSystemEntryType::File
+    ) {
+        if let FileContent::Content(file) = &*package_json_path.read().await? {
+            if let Some(query) = browserslist_from_package_json(file.content().to_str()?.as_ref())?
+            {
+                return Ok(Vc::cell(Some(query.into())));
+            }
+        }
+    }
+
+    Ok(Vc::cell(None))
+}
+
+/// Parses the newline-delimited, `#`-comment-supporting format of a
+/// `.browserslistrc` file into a single comma-separated query.
+fn parse_browserslistrc(contents: &str) -> Option<String> {
+    let query = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect::<Vec<_>>()
+        .join(", ");
+    (!query.is_empty()).then_some(query)
+}
+
+/// Parses the `browserslist` field of a `package.json`, if present, into a
+/// single comma-separated query.
+fn browserslist_from_package_json(contents: &str) -> Result<Option<String>> {
+    let package_json: serde_json::Value = serde_json::from_str(contents)?;
+    let query = match package_json.get("browserslist") {
+        Some(serde_json::Value::Array(entries)) => entries
+            .iter()
+            .filter_map(|entry| entry.as_str())
+            .collect::<Vec<_>>()
+            .join(", "),
+        Some(serde_json::Value::String(query)) => query.clone(),
+        _ => String::new(),
+    };
+    Ok((!query.is_empty()).then_some(query))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{browserslist_from_package_json, parse_browserslistrc};
+
+    #[test]
+    fn parses_browserslistrc_ignoring_blank_lines_and_comments() {
+        let contents = "\n# comment\nlast 1 Chrome versions\n\nlast 1 Firefox versions\n";
+        assert_eq!(
+            parse_browserslistrc(contents).as_deref(),
+            Some("last 1 Chrome versions, last 1 Firefox versions")
+        );
+    }
+
+    #[test]
+    fn empty_browserslistrc_is_none() {
+        assert_eq!(parse_browserslistrc("\n# only comments\n"), None);
+    }
+
+    #[test]
+    fn reads_browserslist_array_from_package_json() {
+        let contents = r#"{"browserslist": ["last 1 Chrome versions", "last 1 Firefox versions"]}"#;
+        assert_eq!(
+            browserslist_from_package_json(contents).unwrap().as_deref(),
+            Some("last 1 Chrome versions, last 1 Firefox versions")
+        );
+    }
+
+    #[test]
+    fn reads_browserslist_string_from_package_json() {
+        let contents = r#"{"browserslist": "last 1 Chrome versions"}"#;
+        assert_eq!(
+            browserslist_from_package_json(contents).unwrap().as_deref(),
+            Some("last 1 Chrome versions")
+        );
+    }
+
+    #[test]
+    fn missing_browserslist_field_is_none() {
+        assert_eq!(browserslist_from_package_json("{}").unwrap(), None);
     }
 }
 
@@ -0,0 +1,77 @@
+use std::{fmt, ops::Deref, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+use turbo_tasks::{
+    trace::{TraceRawVcs, TraceRawVcsContext},
+    TaskInput,
+};
+
+/// A reference-counted, immutable string. Cloning is a refcount bump rather
+/// than a heap allocation, and all clones of one `RcStr` share its backing
+/// allocation, which matters for fields like
+/// [`crate::project::ProjectOptions::root_path`] that get cloned into many
+/// cached turbo-tasks cells.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, TaskInput)]
+#[serde(transparent)]
+pub struct RcStr(Arc<str>);
+
+impl From<String> for RcStr {
+    fn from(value: String) -> Self {
+        RcStr(value.into())
+    }
+}
+
+impl From<&str> for RcStr {
+    fn from(value: &str) -> Self {
+        RcStr(value.into())
+    }
+}
+
+impl Deref for RcStr {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for RcStr {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for RcStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl TraceRawVcs for RcStr {
+    fn trace_raw_vcs(&self, _trace_context: &mut TraceRawVcsContext) {
+        // A plain string holds no `Vc`s to trace.
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RcStr;
+
+    #[test]
+    fn displays_as_the_underlying_str() {
+        assert_eq!(RcStr::from("hello").to_string(), "hello");
+    }
+
+    #[test]
+    fn derefs_to_str() {
+        let value: RcStr = "hello".into();
+        assert_eq!(value.len(), 5);
+        assert!(value.starts_with("hel"));
+    }
+
+    #[test]
+    fn equality_is_by_value() {
+        assert_eq!(RcStr::from("hello"), RcStr::from("hello".to_string()));
+        assert_ne!(RcStr::from("hello"), RcStr::from("world"));
+    }
+}
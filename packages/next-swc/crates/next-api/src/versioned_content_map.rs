@@ -0,0 +1,75 @@
+use anyhow::Result;
+use indexmap::IndexMap;
+use turbo_tasks::{State, TransientInstance, Vc};
+use turbopack_binding::{
+    turbo::tasks_fs::FileSystemPath,
+    turbopack::core::version::{Update, Version, VersionedContent},
+};
+
+/// Holds the output assets endpoints write into as they compute them, keyed
+/// by output path, so `Project::hmr_events` can read current content back
+/// without a dev server. Populated today by `MiddlewareEndpoint::write_to_disk`;
+/// `AppEndpoint`/`PagesEndpoint` need an equivalent `insert_path` call at
+/// their own write sites to cover app/pages routes too.
+#[turbo_tasks::value(transient)]
+pub struct VersionedContentMap {
+    map: State<IndexMap<Vc<FileSystemPath>, Vc<Box<dyn VersionedContent>>>>,
+}
+
+#[turbo_tasks::value(transparent)]
+pub struct OptionVersionedContent(Option<Vc<Box<dyn VersionedContent>>>);
+
+#[turbo_tasks::value_impl]
+impl VersionedContentMap {
+    #[turbo_tasks::function]
+    pub fn empty() -> Vc<Self> {
+        VersionedContentMap {
+            map: State::new(IndexMap::new()),
+        }
+        .cell()
+    }
+
+    /// Inserts or replaces the versioned content written to `path`.
+    pub async fn insert_path(
+        self: Vc<Self>,
+        path: Vc<FileSystemPath>,
+        content: Vc<Box<dyn VersionedContent>>,
+    ) -> Result<()> {
+        let this = self.await?;
+        this.map.update_conditionally(|map| {
+            if let Some(existing) = map.get(&path) {
+                if *existing == content {
+                    return false;
+                }
+            }
+            map.insert(path, content);
+            true
+        });
+        Ok(())
+    }
+
+    /// Returns the versioned content currently stored at `path`, or `None` if
+    /// no endpoint has written to it yet.
+    #[turbo_tasks::function]
+    pub async fn get(self: Vc<Self>, path: Vc<FileSystemPath>) -> Result<Vc<OptionVersionedContent>> {
+        let this = self.await?;
+        let map = this.map.get();
+        Ok(Vc::cell(map.get(&path).copied()))
+    }
+
+    /// Computes the [`Update`] between `from` and the content currently
+    /// stored at `path`. Returns [`Update::Missing`] if nothing has been
+    /// written to `path` yet (e.g. the endpoint hasn't finished its first
+    /// computation).
+    #[turbo_tasks::function]
+    pub async fn update(
+        self: Vc<Self>,
+        path: Vc<FileSystemPath>,
+        from: TransientInstance<Box<dyn Version>>,
+    ) -> Result<Vc<Update>> {
+        let Some(content) = &*self.get(path).await? else {
+            return Ok(Update::Missing.cell());
+        };
+        Ok(content.update(Vc::cell(from.into_owned())))
+    }
+}